@@ -1,11 +1,41 @@
 use crate::rgb;
 use crate::core;
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::f32::consts::{E, PI};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
 
 const ANIMATIONS_DELAY_MS : u128 = 33; // 33 ms ~= 30fps
 
+// A per-key opacity mask (0 = fully transparent, 255 = fully opaque) for one
+// layer. Implemented for `[u8; 90]` directly and for `[bool; 90]` so existing
+// callers that pass `enabled_keys` keep compiling unchanged: `true` maps to
+// 255 (opaque) and `false` to 0 (transparent), which reproduces the old
+// hard-selection behaviour as a special case of blending.
+pub trait AlphaMask {
+    fn to_alpha(&self) -> [u8; 90];
+}
+
+impl AlphaMask for [u8; 90] {
+    fn to_alpha(&self) -> [u8; 90] {
+        *self
+    }
+}
+
+impl AlphaMask for [bool; 90] {
+    fn to_alpha(&self) -> [u8; 90] {
+        let mut alpha = [0u8; 90];
+        for x in 0..90 {
+            alpha[x] = if self[x] { 255 } else { 0 };
+        }
+        alpha
+    }
+}
+
 pub struct EffectManager {
-    layerHistory: Vec<[u8; 90]>,
+    layerAlpha: Vec<[u8; 90]>, // Per-layer, per-key opacity; layerAlpha[i] belongs to effects[i]
     effects: Vec<Box<dyn Effect>>,
     lastUpdateTime: u128,
     combined: rgb::KeyboardData, // Actual rendered keyboard
@@ -18,7 +48,7 @@ impl EffectManager {
 
     pub fn new() -> EffectManager {
         EffectManager {
-            layerHistory: vec![],
+            layerAlpha: vec![],
             effects: vec![],
             lastUpdateTime: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis(),
             combined: rgb::KeyboardData::new()
@@ -27,16 +57,25 @@ impl EffectManager {
 
     pub fn update(&mut self, handler: &mut core::DriverHandler) {
         if EffectManager::get_millis() - self.lastUpdateTime >= ANIMATIONS_DELAY_MS {
-            if self.layerHistory.len() == 0 { return } // Return if we have no effects!
+            if self.effects.len() == 0 { return } // Return if we have no effects!
             // Update all our effects
             // Create a temp map of keyboard
             let mut keyboards : Vec<rgb::KeyboardData> = self.effects.iter_mut().map(|x| x.update()).collect();
 
-            for (key_index, layer_index) in self.layerHistory.last().unwrap().iter().enumerate() {
-                self.combined.set_key_at(key_index, keyboards[*layer_index as usize].get_key_at(key_index))
+            // Composite bottom-up starting from the bottom layer: each layer above
+            // blends over the accumulated result using its own per-key alpha mask.
+            for key_index in 0..90 {
+                let (mut out_r, mut out_g, mut out_b) = keyboards[0].get_key_at(key_index);
+                for layer_index in 1..keyboards.len() {
+                    let alpha = self.layerAlpha[layer_index][key_index] as f32 / 255.0;
+                    let (src_r, src_g, src_b) = keyboards[layer_index].get_key_at(key_index);
+                    out_r = (src_r as f32 * alpha + out_r as f32 * (1.0 - alpha)) as u8;
+                    out_g = (src_g as f32 * alpha + out_g as f32 * (1.0 - alpha)) as u8;
+                    out_b = (src_b as f32 * alpha + out_b as f32 * (1.0 - alpha)) as u8;
+                }
+                self.combined.set_key_at(key_index, (out_r, out_g, out_b));
             }
 
-
             self.combined.update_kbd(handler); // Render keyboard
             self.lastUpdateTime = EffectManager::get_millis();
         }
@@ -46,24 +85,31 @@ impl EffectManager {
         self.effects.len()
     }
 
-    pub fn push_effect(&mut self, newEffect: Box<dyn Effect>, enabled_keys: &[bool; 90]) {
+    // `mask` accepts either a `[u8; 90]` per-key alpha mask or a `[bool; 90]`
+    // enabled-keys mask (see `AlphaMask`). The bottom-most layer is always
+    // fully opaque since there's nothing beneath it to blend with.
+    pub fn push_effect<M: AlphaMask>(&mut self, newEffect: Box<dyn Effect>, mask: &M) {
         self.effects.push(newEffect);
-        if self.layerHistory.len() == 0 { // No previous effects stored?
-            self.layerHistory.push([0; 90]); // Push empty array of all keys
-        } else { // Existing effect found. Merge layers
-            let new_layer_id = (self.effects.len()-1) as usize;
-            self.layerHistory.push(self.layerHistory.last().unwrap().clone()); // Create a copy of the previous history
-            for x in 0..90 { // Iterate over all keys
-                if enabled_keys[x] == true { // Found a new key that uses the new layer
-                    self.layerHistory[new_layer_id][x] = new_layer_id as u8; // Set the key to use the top-most layer
-                }
-            }
-        }
+        self.layerAlpha.push(mask.to_alpha());
+    }
+
+    // Convenience for a uniform per-effect opacity instead of a per-key mask.
+    pub fn push_effect_with_opacity(&mut self, new_effect: Box<dyn Effect>, opacity: u8) {
+        self.push_effect(new_effect, &[opacity; 90]);
     }
 
     pub fn pop_effect(&mut self) {
         self.effects.pop();
-        self.layerHistory.pop();
+        self.layerAlpha.pop();
+    }
+
+    // Forwards a keystroke to every layer so reactive effects (e.g. `ReactiveEffect`)
+    // can light up the struck key. Non-reactive effects just ignore it via the
+    // trait's default no-op implementation.
+    pub fn on_key_event(&mut self, key_index: usize) {
+        for effect in self.effects.iter_mut() {
+            effect.on_key_event(key_index);
+        }
     }
 }
 
@@ -76,6 +122,10 @@ pub enum EffectDir {
 
 pub trait Effect {
     fn update(&mut self) -> rgb::KeyboardData;
+
+    // Called whenever a keystroke is observed. Most effects don't care, so
+    // this defaults to a no-op; reactive effects override it to record the hit.
+    fn on_key_event(&mut self, _key_index: usize) {}
 }
 
 // -- Static effect code --
@@ -101,6 +151,31 @@ impl Effect for StaticEffect {
     }
 }
 
+// Normalized physical (x, y) position of each of the 90 keys, laid out as a
+// 6 row x 15 column grid in [0,1] on both axes. Shared by any effect that
+// needs to blend or animate along the keyboard's physical geometry rather
+// than its row/column indices (diagonal & circular gradients, ripples, ...).
+pub const KEY_COORDS: [(f32, f32); 90] = [
+    (0.0, 0.0), (0.0714, 0.0), (0.1429, 0.0), (0.2143, 0.0), (0.2857, 0.0),
+    (0.3571, 0.0), (0.4286, 0.0), (0.5, 0.0), (0.5714, 0.0), (0.6429, 0.0),
+    (0.7143, 0.0), (0.7857, 0.0), (0.8571, 0.0), (0.9286, 0.0), (1.0, 0.0),
+    (0.0, 0.2), (0.0714, 0.2), (0.1429, 0.2), (0.2143, 0.2), (0.2857, 0.2),
+    (0.3571, 0.2), (0.4286, 0.2), (0.5, 0.2), (0.5714, 0.2), (0.6429, 0.2),
+    (0.7143, 0.2), (0.7857, 0.2), (0.8571, 0.2), (0.9286, 0.2), (1.0, 0.2),
+    (0.0, 0.4), (0.0714, 0.4), (0.1429, 0.4), (0.2143, 0.4), (0.2857, 0.4),
+    (0.3571, 0.4), (0.4286, 0.4), (0.5, 0.4), (0.5714, 0.4), (0.6429, 0.4),
+    (0.7143, 0.4), (0.7857, 0.4), (0.8571, 0.4), (0.9286, 0.4), (1.0, 0.4),
+    (0.0, 0.6), (0.0714, 0.6), (0.1429, 0.6), (0.2143, 0.6), (0.2857, 0.6),
+    (0.3571, 0.6), (0.4286, 0.6), (0.5, 0.6), (0.5714, 0.6), (0.6429, 0.6),
+    (0.7143, 0.6), (0.7857, 0.6), (0.8571, 0.6), (0.9286, 0.6), (1.0, 0.6),
+    (0.0, 0.8), (0.0714, 0.8), (0.1429, 0.8), (0.2143, 0.8), (0.2857, 0.8),
+    (0.3571, 0.8), (0.4286, 0.8), (0.5, 0.8), (0.5714, 0.8), (0.6429, 0.8),
+    (0.7143, 0.8), (0.7857, 0.8), (0.8571, 0.8), (0.9286, 0.8), (1.0, 0.8),
+    (0.0, 1.0), (0.0714, 1.0), (0.1429, 1.0), (0.2143, 1.0), (0.2857, 1.0),
+    (0.3571, 1.0), (0.4286, 1.0), (0.5, 1.0), (0.5714, 1.0), (0.6429, 1.0),
+    (0.7143, 1.0), (0.7857, 1.0), (0.8571, 1.0), (0.9286, 1.0), (1.0, 1.0),
+];
+
 // -- 'Blend' effect code --
 pub struct BlendEffect {
     pub kbd: rgb::KeyboardData
@@ -116,27 +191,41 @@ impl BlendEffect {
             EffectDir::Vertical => {
                 for x in 0..6 {
                     let col_blend_ratio = (x+1) as f32 / 6.0;
-                    k.set_row_colour(x, 
+                    k.set_row_colour(x,
                         (r1 as f32 + (dr * col_blend_ratio)) as u8,
                         (g1 as f32 + (dg * col_blend_ratio)) as u8,
                         (b1 as f32 + (db * col_blend_ratio)) as u8);
-    
+
                 }
             },
             EffectDir::Horizontal => {
                 for x in 0..15 {
                     let col_blend_ratio = (x+1) as f32 / 15.0;
-                    k.set_col_colour(x, 
+                    k.set_col_colour(x,
                         (r1 as f32 + (dr * col_blend_ratio)) as u8,
                         (g1 as f32 + (dg * col_blend_ratio)) as u8,
                         (b1 as f32 + (db * col_blend_ratio)) as u8);
-    
+
+                }
+            },
+            EffectDir::Diagonal => {
+                for (key_index, (x, y)) in KEY_COORDS.iter().enumerate() {
+                    let ratio = (x + y) / 2.0;
+                    k.set_key_at(key_index, (
+                        (r1 as f32 + (dr * ratio)) as u8,
+                        (g1 as f32 + (dg * ratio)) as u8,
+                        (b1 as f32 + (db * ratio)) as u8));
                 }
             },
-            _ => { 
-                // Unsupported direction, default to vertical
-                eprintln!("BlendMode Diagonal unsupported, using vertical");
-                return BlendEffect::new(r1, g1, b1, r2, g2, b2, EffectDir::Vertical) 
+            EffectDir::Circular => {
+                let max_dist = (0.5_f32).hypot(0.5);
+                for (key_index, (x, y)) in KEY_COORDS.iter().enumerate() {
+                    let ratio = (x - 0.5).hypot(y - 0.5) / max_dist;
+                    k.set_key_at(key_index, (
+                        (r1 as f32 + (dr * ratio)) as u8,
+                        (g1 as f32 + (dg * ratio)) as u8,
+                        (b1 as f32 + (db * ratio)) as u8));
+                }
             }
         }
         BlendEffect {
@@ -153,63 +242,368 @@ impl Effect for BlendEffect {
 }
 
 // -- 'Breathing' effect
+// Builds the perceptual brightness curve QMK uses for its breathing animation:
+// an exponential-of-sine wave, so the ramp looks smooth to the eye instead of
+// jerky like a plain triangle wave would.
+fn build_breathe_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for i in 0..256 {
+        let x = (i as f32 / 255.0) * 2.0 * PI;
+        let s = ((x.sin().exp() - 1.0 / E) * (255.0 / (E - 1.0 / E))) as u8;
+        table[i] = s;
+    }
+    table
+}
+
 pub struct BreathEffect {
     pub kbd: rgb::KeyboardData,
-    step_duration_ms: u128,
-    step_start_ms: u128,
-    curr_step: u8, // Step 0 = Off, 1 = increasing, 2 = On, 3 = decreasing
+    cycle_duration_ms: u128,
+    start_ms: u128,
     targ_red: f32,
     targ_green: f32,
     targ_blue: f32,
-    curr_red: f32,
-    curr_green: f32,
-    curr_blue: f32
+    breathe_table: [u8; 256]
 }
 
 impl BreathEffect {
     pub fn new(red: u8, green: u8, blue: u8, cycle_duration_ms: u32) -> BreathEffect {
-        let mut k =  rgb::KeyboardData::new();
+        let mut k = rgb::KeyboardData::new();
         k.set_kbd_colour(0, 0, 0); // Sets all keyboard lights off initially
         return BreathEffect {
             kbd: k,
-            step_duration_ms: cycle_duration_ms as u128,
-            step_start_ms : EffectManager::get_millis(),
-            curr_step: 0,
+            cycle_duration_ms: (cycle_duration_ms as u128).max(1), // Avoid a div-by-zero panic in update()
+            start_ms: EffectManager::get_millis(),
             targ_red: red as f32,
             targ_green: green as f32,
             targ_blue: blue as f32,
-            curr_red : 0.0,
-            curr_green: 0.0,
-            curr_blue: 0.0
+            breathe_table: build_breathe_table()
         }
     }
 }
 
 impl Effect for BreathEffect {
     fn update(&mut self) -> rgb::KeyboardData {
-        if EffectManager::get_millis() - self.step_duration_ms >= self.step_duration_ms { // Time to change keyboard's phase
-            self.curr_step += 1;
-            if self.curr_step == 4 {
-                self.curr_step = 0 // Reset step
+        let elapsed = EffectManager::get_millis() - self.start_ms;
+        let i = ((elapsed % self.cycle_duration_ms) * 256 / self.cycle_duration_ms) as usize;
+        let s = self.breathe_table[i] as f32 / 255.0;
+        self.kbd.set_kbd_colour(
+            (self.targ_red * s) as u8,
+            (self.targ_green * s) as u8,
+            (self.targ_blue * s) as u8
+        );
+        return self.kbd;
+    }
+}
+
+// -- 'Reactive' effect code --
+// Distance (in normalized KEY_COORDS units) on either side of a ripple's
+// wavefront over which a key's brightness falls linearly from full to zero.
+// Wider band = softer ripple edge.
+const RIPPLE_BAND_WIDTH: f32 = 0.15;
+
+pub struct ReactiveEffect {
+    pub kbd: rgb::KeyboardData,
+    base_red: f32,
+    base_green: f32,
+    base_blue: f32,
+    fade_ms: u128,
+    ripple_speed: f32, // normalized KEY_COORDS units per ms the ripple wavefront travels
+    last_hit_ms: [u128; 90]
+}
+
+impl ReactiveEffect {
+    pub fn new(red: u8, green: u8, blue: u8, fade_ms: u128, ripple_speed: f32) -> ReactiveEffect {
+        let mut k = rgb::KeyboardData::new();
+        k.set_kbd_colour(0, 0, 0); // Sets all keyboard lights off initially
+        ReactiveEffect {
+            kbd: k,
+            base_red: red as f32,
+            base_green: green as f32,
+            base_blue: blue as f32,
+            fade_ms,
+            ripple_speed,
+            last_hit_ms: [0; 90]
+        }
+    }
+}
+
+impl Effect for ReactiveEffect {
+    fn update(&mut self) -> rgb::KeyboardData {
+        let now = EffectManager::get_millis();
+        let mut brightness = [0.0f32; 90];
+
+        // Every struck key is its own ripple source: a fading wavefront that
+        // expands outward across KEY_COORDS as time since the keystroke grows.
+        for (src_index, hit_time) in self.last_hit_ms.iter().enumerate() {
+            if *hit_time == 0 {
+                continue;
+            }
+            // `hit_time` is stamped by `on_key_event` from the wall clock, which is not
+            // monotonic - saturate rather than risk underflowing if the clock has
+            // stepped back so that a recorded hit is later than `now`.
+            let elapsed = now.saturating_sub(*hit_time) as f32;
+            if elapsed >= self.fade_ms as f32 {
+                continue;
+            }
+            let fade = 1.0 - elapsed / self.fade_ms as f32;
+            let radius = elapsed * self.ripple_speed;
+            let (sx, sy) = KEY_COORDS[src_index];
+            for (key_index, (x, y)) in KEY_COORDS.iter().enumerate() {
+                let dist = (x - sx).hypot(y - sy);
+                let ripple = (1.0 - (dist - radius).abs() / RIPPLE_BAND_WIDTH).max(0.0);
+                let b = fade * ripple;
+                if b > brightness[key_index] {
+                    brightness[key_index] = b;
+                }
             }
         }
-        let step_red = self.targ_red / (self.step_duration_ms as f32 / ANIMATIONS_DELAY_MS as f32);
-        let step_green = self.targ_green / (self.step_duration_ms as f32 / ANIMATIONS_DELAY_MS as f32);
-        let step_blue = self.targ_blue / (self.step_duration_ms as f32 / ANIMATIONS_DELAY_MS as f32);
-        match self.curr_step {
-            1 => { // Increasing
-                self.curr_red += step_red;
-                self.curr_green += step_green;
-                self.curr_blue += step_blue;
-            },
-            3 => { // Decreasing
-                self.curr_red -= step_red;
-                self.curr_green -= step_green;
-                self.curr_blue -= step_blue;
+
+        for (key_index, b) in brightness.iter().enumerate() {
+            self.kbd.set_key_at(key_index, (
+                (self.base_red * b) as u8,
+                (self.base_green * b) as u8,
+                (self.base_blue * b) as u8));
+        }
+        return self.kbd;
+    }
+
+    fn on_key_event(&mut self, key_index: usize) {
+        if key_index < self.last_hit_ms.len() {
+            self.last_hit_ms[key_index] = EffectManager::get_millis();
+        }
+    }
+}
+
+// -- 'Audio spectrum' effect code --
+const AUDIO_FFT_SIZE: usize = 1024;
+const AUDIO_BANDS: usize = 15; // One band per keyboard column
+const AUDIO_ROWS: usize = 6;
+
+pub struct AudioSpectrumEffect {
+    pub kbd: rgb::KeyboardData,
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    _stream: Option<cpal::Stream>, // Kept alive so capture keeps running; dropping it stops the stream
+    fft: Arc<dyn Fft<f32>>,
+    band_energy: [f32; AUDIO_BANDS],
+    sensitivity: f32,
+    decay: f32,
+    r1: u8, g1: u8, b1: u8, // Bottom-of-column colour
+    r2: u8, g2: u8, b2: u8, // Top-of-column colour
+}
+
+impl AudioSpectrumEffect {
+    pub fn new(sensitivity: f32, decay: f32, r1: u8, g1: u8, b1: u8, r2: u8, g2: u8, b2: u8) -> AudioSpectrumEffect {
+        let mut k = rgb::KeyboardData::new();
+        k.set_kbd_colour(0, 0, 0); // Stay dark until we have audio to show
+
+        let samples = Arc::new(Mutex::new(VecDeque::with_capacity(AUDIO_FFT_SIZE * 2)));
+        // open_capture_stream() logs the specific reason (no device, unsupported
+        // sample format, stream build/start failure) itself before returning None.
+        let stream = AudioSpectrumEffect::open_capture_stream(samples.clone());
+
+        let mut planner = FftPlanner::new();
+        AudioSpectrumEffect {
+            kbd: k,
+            samples,
+            _stream: stream,
+            fft: planner.plan_fft_forward(AUDIO_FFT_SIZE),
+            band_energy: [0.0; AUDIO_BANDS],
+            sensitivity,
+            decay,
+            r1, g1, b1,
+            r2, g2, b2,
+        }
+    }
+
+    // Pushes captured samples (already converted to f32) into the shared ring
+    // buffer, trimming it back down to our FFT working set.
+    fn push_samples(samples: &Arc<Mutex<VecDeque<f32>>>, data: impl Iterator<Item = f32>) {
+        let mut buf = samples.lock().unwrap();
+        buf.extend(data);
+        while buf.len() > AUDIO_FFT_SIZE * 2 {
+            buf.pop_front();
+        }
+    }
+
+    fn open_capture_stream(samples: Arc<Mutex<VecDeque<f32>>>) -> Option<cpal::Stream> {
+        let device = match cpal::default_host().default_input_device() {
+            Some(device) => device,
+            None => {
+                eprintln!("AudioSpectrumEffect: no audio input device available, staying dark");
+                return None;
+            }
+        };
+        let config = match device.default_input_config() {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("AudioSpectrumEffect: could not read default input config: {}, staying dark", err);
+                return None;
+            }
+        };
+
+        // The device's default config dictates the sample format cpal will hand us;
+        // build the matching stream type rather than assuming f32 (plain ALSA
+        // devices without a float shim commonly default to I16/U16).
+        let sample_format = config.sample_format();
+        let stream_config: cpal::StreamConfig = config.into();
+        let err_fn = |err| eprintln!("AudioSpectrumEffect: input stream error: {}", err);
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &stream_config,
+                move |data: &[f32], _| AudioSpectrumEffect::push_samples(&samples, data.iter().copied()),
+                err_fn,
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &stream_config,
+                move |data: &[i16], _| AudioSpectrumEffect::push_samples(&samples, data.iter().map(|s| *s as f32 / i16::MAX as f32)),
+                err_fn,
+                None,
+            ),
+            cpal::SampleFormat::U16 => device.build_input_stream(
+                &stream_config,
+                move |data: &[u16], _| AudioSpectrumEffect::push_samples(&samples, data.iter().map(|s| (*s as f32 / u16::MAX as f32) * 2.0 - 1.0)),
+                err_fn,
+                None,
+            ),
+            other => {
+                eprintln!("AudioSpectrumEffect: unsupported input sample format {:?}, staying dark", other);
+                return None;
+            }
+        };
+
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("AudioSpectrumEffect: failed to build input stream: {}, staying dark", err);
+                return None;
             }
-            _ => {} // Other state which is static
+        };
+        if let Err(err) = stream.play() {
+            eprintln!("AudioSpectrumEffect: failed to start input stream: {}, staying dark", err);
+            return None;
+        }
+        Some(stream)
+    }
+
+    // Hann-windowed FFT of the latest AUDIO_FFT_SIZE samples, grouped
+    // logarithmically into AUDIO_BANDS magnitude bands.
+    fn compute_bands(&self) -> [f32; AUDIO_BANDS] {
+        let mut bands = [0.0f32; AUDIO_BANDS];
+        let buf = self.samples.lock().unwrap();
+        if buf.len() < AUDIO_FFT_SIZE {
+            return bands; // Not enough audio yet, report silence
+        }
+
+        let start = buf.len() - AUDIO_FFT_SIZE;
+        let mut fft_buf: Vec<Complex<f32>> = (0..AUDIO_FFT_SIZE).map(|i| {
+            let sample = buf[start + i];
+            let window = 0.5 - 0.5 * ((2.0 * PI * i as f32) / (AUDIO_FFT_SIZE - 1) as f32).cos(); // Hann window
+            Complex::new(sample * window, 0.0)
+        }).collect();
+        self.fft.process(&mut fft_buf);
+
+        // Bin 0 is DC, bin AUDIO_FFT_SIZE/2 is Nyquist; group the usable bins
+        // logarithmically so each band covers a musically even range.
+        let usable_bins = AUDIO_FFT_SIZE / 2;
+        let log_max = (usable_bins as f32).ln();
+        for band in 0..AUDIO_BANDS {
+            let lo = ((band as f32 / AUDIO_BANDS as f32) * log_max).exp().max(1.0) as usize;
+            let hi = (((band + 1) as f32 / AUDIO_BANDS as f32) * log_max).exp().max(2.0) as usize;
+            let hi = hi.min(usable_bins).max(lo + 1);
+            let magnitude: f32 = fft_buf[lo..hi].iter().map(|c| c.norm()).sum::<f32>() / (hi - lo) as f32;
+            bands[band] = magnitude;
+        }
+        bands
+    }
+}
+
+impl Effect for AudioSpectrumEffect {
+    fn update(&mut self) -> rgb::KeyboardData {
+        let new_bands = self.compute_bands();
+        for band in 0..AUDIO_BANDS {
+            // Exponential moving average: smooths band energy across frames
+            // instead of letting the display jitter with every FFT frame.
+            self.band_energy[band] = self.band_energy[band] * (1.0 - self.decay) + new_bands[band] * self.decay;
+        }
+
+        for col in 0..AUDIO_BANDS {
+            let ratio = (self.band_energy[col] * self.sensitivity).clamp(0.0, 1.0);
+            let lit_rows = (ratio * AUDIO_ROWS as f32).round() as usize;
+            for row in 0..AUDIO_ROWS {
+                let key_index = row * AUDIO_BANDS + col;
+                let row_from_bottom = AUDIO_ROWS - 1 - row;
+                if row_from_bottom < lit_rows {
+                    let height_ratio = row_from_bottom as f32 / (AUDIO_ROWS - 1) as f32;
+                    self.kbd.set_key_at(key_index, (
+                        (self.r1 as f32 + (self.r2 as f32 - self.r1 as f32) * height_ratio) as u8,
+                        (self.g1 as f32 + (self.g2 as f32 - self.g1 as f32) * height_ratio) as u8,
+                        (self.b1 as f32 + (self.b2 as f32 - self.b1 as f32) * height_ratio) as u8));
+                } else {
+                    self.kbd.set_key_at(key_index, (0, 0, 0));
+                }
+            }
+        }
+        return self.kbd;
+    }
+}
+
+// Standard HSV -> RGB conversion (h, s, v all in [0,1]); used by effects that
+// animate a moving hue rather than blending between two fixed colours.
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
+    let h = h.fract() * 6.0;
+    let i = h.floor() as i32;
+    let f = h - i as f32;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+    let (r, g, b) = match i.rem_euclid(6) {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    };
+    ((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+}
+
+// -- 'Wave' effect code --
+// An animated rainbow/swirl: each key's hue is its position along `dir` (using
+// KEY_COORDS for Diagonal/Circular) plus a phase that advances every frame, so
+// the gradient continuously scrolls across the keyboard instead of sitting still.
+pub struct WaveEffect {
+    pub kbd: rgb::KeyboardData,
+    dir: EffectDir,
+    cycle_duration_ms: u128,
+    phase: f32 // Current hue offset, wraps around in [0, 1)
+}
+
+impl WaveEffect {
+    pub fn new(cycle_duration_ms: u32, dir: EffectDir) -> WaveEffect {
+        WaveEffect {
+            kbd: rgb::KeyboardData::new(),
+            dir,
+            cycle_duration_ms: (cycle_duration_ms as u128).max(1), // Avoid a div-by-zero panic in update()
+            phase: 0.0
+        }
+    }
+}
+
+impl Effect for WaveEffect {
+    fn update(&mut self) -> rgb::KeyboardData {
+        self.phase = (self.phase + ANIMATIONS_DELAY_MS as f32 / self.cycle_duration_ms as f32).fract();
+
+        for (key_index, (x, y)) in KEY_COORDS.iter().enumerate() {
+            let position = match self.dir {
+                EffectDir::Vertical => *y,
+                EffectDir::Horizontal => *x,
+                EffectDir::Diagonal => (x + y) / 2.0,
+                EffectDir::Circular => (x - 0.5).hypot(y - 0.5) / (0.5_f32).hypot(0.5)
+            };
+            let hue = (position + self.phase).fract();
+            self.kbd.set_key_at(key_index, hsv_to_rgb(hue, 1.0, 1.0));
         }
-        self.kbd.set_kbd_colour(self.curr_red as u8, self.curr_green as u8, self.curr_blue as u8); // Cast back to u8
         return self.kbd;
     }
 }